@@ -1,7 +1,7 @@
 //! # About
 //! This crate provides the [`Winit`](crate::Winit) widget,
 //! a [`canvas`](leptos::html::Canvas) element with a
-//! [`Window`](winit::window::Window) and
+//! [`Window`](winit::window::Window) driven by a shared
 //! [`EventLoop`](winit::event_loop::EventLoop).
 //!
 //! # Example
@@ -9,137 +9,856 @@
 //! use leptos::*;
 //! use leptos_winit::*;
 //! use std::rc::Rc;
-//! use winit::event_loop::EventLoop;
+//! use winit::event::WindowEvent;
 //! use winit::window::Window;
 //!
 //! fn main() {
 //!   mount_to_body(|| {
 //!     view!{
 //!       <Winit
-//!          program=run // Required
+//!          app=Game // Required, implements App<()>
 //!          // Optional, also accepts signals
 //!          width=500 // Into<u32>
 //!          height=500 // Into<u32>
 //!          alt="Window title" // Into<String>
+//!          mode=UpdateMode::Continuous // UpdateMode, not a signal
 //!       />
 //!     }
 //!   });
 //! }
 //!
-//! // Changing the user event type `T` in `EventLoop<T>` is allowed
-//! async fn run(event_loop: EventLoop<()>, window: Rc<Window>) {
-//!   // Initialize wgpu, pixels, etc...
-//!   event_loop.run(move |_event, _target, _control| {
-//!     todo!(); // Event loop runs without blocking
-//!   });
+//! struct Game;
+//! impl App<()> for Game {
+//!   // Called the first time a surface is available, and again after
+//!   // `suspended` if the tab was backgrounded and the surface was lost.
+//!   fn resumed(&mut self, window: Rc<Window>) {
+//!     // Initialize wgpu, pixels, etc...
+//!   }
+//!   fn window_event(&mut self, window: &Rc<Window>, event: WindowEvent) {
+//!     todo!(); // Handle input, resize, RedrawRequested...
+//!   }
 //! }
 //! ```
 //! # Multiple windows
-//! Winit does not support creating multiple
-//! [`EventLoop`](winit::event_loop::EventLoop)s, so
-//! only one `Winit` widget can be loaded on the page at a
-//! time. You can get around this by placing windows on
-//! seperate routes using `leptos_router`.
+//! Winit forbids creating multiple
+//! [`EventLoop`](winit::event_loop::EventLoop)s, but a single
+//! loop is allowed to own many [`Window`](winit::window::Window)s.
+//! The first `Winit` widget to mount lazily creates one loop for its
+//! `EventT` and every later `Winit` widget (with the same `EventT`)
+//! just adds another window to it, so several widgets can now share
+//! a page. Each widget's [`App`] only ever hears about its own window.
+//!
+//! # Driving the loop from the UI
+//! Since the shared `EventLoop` is owned internally, the rest of the
+//! Leptos app has no direct way to push events into it. Pass an
+//! `events` signal and whenever it holds `Some(..)`, the value is
+//! forwarded into the loop via
+//! [`EventLoopProxy::send_event`](winit::event_loop::EventLoopProxy::send_event)
+//! and delivered to every widget's [`App::user_event`].
 
 use leptos::html::Canvas;
 use leptos::*;
-use std::future::Future;
+use std::cell::{Cell, RefCell};
 use std::marker::PhantomData;
 use std::rc::Rc;
 use web_sys::HtmlCanvasElement;
-use winit::event_loop::{EventLoop, EventLoopBuilder};
-use winit::window::Window;
+use winit::application::ApplicationHandler;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoopProxy};
+use winit::window::{Window, WindowId};
+
+/// Implemented by a widget's windowing logic under winit's
+/// `ApplicationHandler` model. `resumed`/`suspended` mirror winit's own
+/// lifecycle transitions, which on the web fire when the canvas's GPU
+/// surface is lost (tab backgrounded) and must be rebuilt on return.
+pub trait App<EventT: 'static> {
+  /// A `Window` (and its surface) is available. Called once the widget
+  /// mounts, and again every time the surface is recreated after
+  /// [`suspended`](App::suspended).
+  fn resumed(&mut self, window: Rc<Window>);
+  /// The surface backing `window` was lost; drop anything that
+  /// depended on it. Does nothing by default.
+  fn suspended(&mut self) {}
+  /// A `WindowEvent` addressed to this widget's window.
+  fn window_event(&mut self, window: &Rc<Window>, event: WindowEvent);
+  /// An event sent through the `events` prop's `EventLoopProxy`.
+  /// Does nothing by default.
+  fn user_event(&mut self, event: EventT) {
+    let _ = event;
+  }
+  /// Called once per loop iteration, after all pending events. Useful
+  /// for driving per-frame logic when [`UpdateMode::Continuous`] keeps
+  /// the loop ticking. Does nothing by default.
+  fn about_to_wait(&mut self, window: &Rc<Window>) {
+    let _ = window;
+  }
+}
+
+/// How often the shared `EventLoop` wakes up to redraw this widget's
+/// window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UpdateMode {
+  /// Request a redraw every loop iteration, i.e. a constant render
+  /// loop. Simplest for games and constantly-animating scenes.
+  #[default]
+  Continuous,
+  /// Only redraw in response to input, resize, or an explicit
+  /// `request_redraw()` from inside [`App`]. Better for battery/CPU.
+  Reactive,
+}
+
+/// Whether the window is windowed or fills the screen. Mirrors
+/// Bevy's `WindowMode`, though on the web both fullscreen variants
+/// map to the same thing: there's only one Fullscreen API, reached
+/// through [`Fullscreen::Borderless`](winit::window::Fullscreen::Borderless).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowMode {
+  #[default]
+  Windowed,
+  BorderlessFullscreen,
+  Fullscreen,
+}
+
+fn window_mode_to_fullscreen(
+  mode: WindowMode,
+) -> Option<winit::window::Fullscreen> {
+  match mode {
+    WindowMode::Windowed => None,
+    WindowMode::BorderlessFullscreen | WindowMode::Fullscreen => {
+      Some(winit::window::Fullscreen::Borderless(None))
+    },
+  }
+}
+
+/// The `on_frame` prop, wrapping any `Fn(f64, Rc<Window>)` closure so
+/// it can be accepted as a plain (non-generic) `Option` prop via
+/// `#[prop(into, optional)]`.
+pub struct OnFrame(Box<dyn Fn(f64, Rc<Window>)>);
+
+/// Where the `Winit` component should get its canvas element from,
+/// resolved once the surrounding view is actually attached to the
+/// document (see its use in [`Winit`]).
+enum CanvasSource {
+  Selector(String),
+  NodeRef(NodeRef<Canvas>),
+}
+
+impl<F: Fn(f64, Rc<Window>) + 'static> From<F> for OnFrame {
+  fn from(callback: F) -> Self {
+    OnFrame(Box::new(callback))
+  }
+}
+
+/// Storage backing the `EventLoop` shared by every `Winit` widget on
+/// the page, plus the windows each widget has asked to be created.
+/// Winit only allows one `EventLoop` per process, and on the new
+/// `ApplicationHandler` model windows can only be created from inside
+/// `resumed`, so widgets register a pending window here and the first
+/// widget's [`RootHandler`] builds them all once the loop starts
+/// running. The state is type-erased because a plain `thread_local!`
+/// can't name the `EventT` of whichever `Winit` widget mounts first.
+mod shared_loop {
+  use super::{App, UpdateMode};
+  use std::any::Any;
+  use std::cell::{Cell, RefCell};
+  use std::rc::Rc;
+  use winit::event_loop::{EventLoop, EventLoopBuilder, EventLoopProxy};
+  use winit::window::{WindowAttributes, WindowId};
+
+  pub struct PendingWindow<EventT: 'static> {
+    /// Shared with the `Winit` widget that registered this window; set
+    /// if the widget unmounts before the window actually gets created,
+    /// so [`super::RootHandler`] knows to skip it instead of building
+    /// (and then immediately orphaning) it.
+    pub cancelled: Rc<Cell<bool>>,
+    pub attributes: WindowAttributes,
+    pub mode: UpdateMode,
+    pub app: Box<dyn App<EventT>>,
+  }
+
+  struct SharedState<EventT: 'static> {
+    event_loop: EventLoop<EventT>,
+    pending: Vec<PendingWindow<EventT>>,
+  }
+
+  thread_local! {
+    static STATE: RefCell<Option<Box<dyn Any>>> = const { RefCell::new(None) };
+    static STATE_TAKEN: Cell<bool> = const { Cell::new(false) };
+    // Type-erased `EventLoopProxy<EventT>` of the already-running loop,
+    // stashed by `take_state` so a widget that mounts afterwards can
+    // still be handed something to forward its `events` prop through.
+    static RUNNING_PROXY: RefCell<Option<Box<dyn Any>>> = const { RefCell::new(None) };
+    // Type-erased `Vec<PendingWindow<EventT>>` queued by `register_window`
+    // once the loop is already running, since `create_window` needs a
+    // live `&ActiveEventLoop` that `register_window`'s caller doesn't have.
+    static LATE_PENDING: RefCell<Option<Box<dyn Any>>> = const { RefCell::new(None) };
+    static REMOVE_QUEUE: RefCell<Vec<WindowId>> = const { RefCell::new(Vec::new()) };
+    static DRIVER_CLAIMED: Cell<bool> = const { Cell::new(false) };
+    static NEXT_CANVAS_ID: Cell<u32> = const { Cell::new(0) };
+  }
+
+  /// Registers a window to be created once the shared loop runs,
+  /// creating that loop first if no `Winit` widget has mounted yet.
+  /// Returns a proxy so the widget can forward its `events` prop.
+  /// `cancelled` is shared with the caller, who should set it on
+  /// unmount if the window hasn't been created yet (see
+  /// [`PendingWindow::cancelled`]).
+  ///
+  /// If the loop is already running (a widget mounting via `<Show>`,
+  /// routing, or any other dynamic update, not just the initial render
+  /// pass), the window is queued instead: winit only hands out
+  /// `&ActiveEventLoop` — the thing `create_window` needs — from inside
+  /// [`super::RootHandler`]'s own callbacks, so the queue is drained
+  /// from `about_to_wait`/`user_event` the next time the loop wakes up.
+  pub fn register_window<EventT: 'static>(
+    attributes: WindowAttributes,
+    mode: UpdateMode,
+    app: Box<dyn App<EventT>>,
+    cancelled: Rc<Cell<bool>>,
+  ) -> EventLoopProxy<EventT> {
+    if STATE_TAKEN.with(Cell::get) {
+      let pending = PendingWindow { cancelled, attributes, mode, app };
+      LATE_PENDING.with(|cell| {
+        let mut slot = cell.borrow_mut();
+        slot
+          .get_or_insert_with(|| Box::new(Vec::<PendingWindow<EventT>>::new()))
+          .downcast_mut::<Vec<PendingWindow<EventT>>>()
+          .expect(
+            "leptos_winit: a Winit widget was mounted with a different \
+             EventT than the already-running shared EventLoop",
+          )
+          .push(pending);
+      });
+      return RUNNING_PROXY.with(|cell| {
+        cell
+          .borrow()
+          .as_ref()
+          .expect("leptos_winit: shared EventLoop taken but no proxy was stashed")
+          .downcast_ref::<EventLoopProxy<EventT>>()
+          .expect(
+            "leptos_winit: a Winit widget was mounted with a different \
+             EventT than the already-running shared EventLoop",
+          )
+          .clone()
+      });
+    }
+    STATE.with(|cell| {
+      let mut slot = cell.borrow_mut();
+      let state = slot
+        .get_or_insert_with(|| {
+          log::warn!(
+            "leptos_winit: creating the shared EventLoop, make sure every \
+             Winit widget on this page uses the same EventT"
+          );
+          Box::new(SharedState::<EventT> {
+            event_loop: EventLoopBuilder::with_user_event()
+              .build()
+              .expect("Failed to initialize winit EventLoop"),
+            pending: Vec::new(),
+          })
+        })
+        .downcast_mut::<SharedState<EventT>>()
+        .expect(
+          "leptos_winit: a Winit widget was mounted with a different \
+           EventT than the already-running shared EventLoop",
+        );
+      state.pending.push(PendingWindow { cancelled, attributes, mode, app });
+      state.event_loop.create_proxy()
+    })
+  }
+
+  /// Takes ownership of the shared `EventLoop<EventT>` and whatever
+  /// windows were registered so far, ready to be run. Only ever called
+  /// once, by whichever widget [`claim_driver`] granted the right to.
+  /// Marks the state as taken for good and stashes a proxy for
+  /// [`register_window`] to hand out to widgets that mount afterwards.
+  pub fn take_state<EventT: 'static>(
+  ) -> (EventLoop<EventT>, Vec<PendingWindow<EventT>>) {
+    STATE.with(|cell| {
+      let boxed = cell
+        .borrow_mut()
+        .take()
+        .expect("leptos_winit: shared EventLoop already taken");
+      STATE_TAKEN.with(|taken| taken.set(true));
+      let state = *boxed.downcast::<SharedState<EventT>>().expect(
+        "leptos_winit: EventT mismatch taking the shared EventLoop",
+      );
+      RUNNING_PROXY.with(|cell| {
+        *cell.borrow_mut() = Some(Box::new(state.event_loop.create_proxy()));
+      });
+      (state.event_loop, state.pending)
+    })
+  }
+
+  /// Takes every window [`register_window`] queued since the shared
+  /// loop started running, ready for [`super::RootHandler`] to build them.
+  pub fn take_late_pending<EventT: 'static>() -> Vec<PendingWindow<EventT>> {
+    LATE_PENDING.with(|cell| {
+      cell
+        .borrow_mut()
+        .take()
+        .map(|boxed| {
+          *boxed.downcast::<Vec<PendingWindow<EventT>>>().expect(
+            "leptos_winit: EventT mismatch taking late-registered windows",
+          )
+        })
+        .unwrap_or_default()
+    })
+  }
+
+  /// Returns `true` exactly once: to the first `Winit` widget to ask,
+  /// which becomes responsible for running the shared loop for every
+  /// widget's windows.
+  pub fn claim_driver() -> bool {
+    DRIVER_CLAIMED.with(|claimed| !claimed.replace(true))
+  }
+
+  /// Requests that the window with this id be dropped from
+  /// [`super::RootHandler`], e.g. because the `Winit` widget that owns
+  /// it just unmounted. `WindowId` isn't generic over `EventT`, so
+  /// unlike the other queues here this one needs no type erasure.
+  pub fn unregister_window(id: WindowId) {
+    REMOVE_QUEUE.with(|queue| queue.borrow_mut().push(id));
+  }
+
+  /// Takes every window id [`unregister_window`] queued since the last
+  /// call, for [`super::RootHandler`] to drop.
+  pub fn take_unregistered() -> Vec<WindowId> {
+    REMOVE_QUEUE.with(|queue| std::mem::take(&mut *queue.borrow_mut()))
+  }
+
+  /// A fresh element id for this widget's canvas, so multiple `Winit`
+  /// widgets no longer collide on a single hard-coded id.
+  pub fn next_canvas_id() -> String {
+    NEXT_CANVAS_ID.with(|next| {
+      let id = next.get();
+      next.set(id + 1);
+      format!("Winit-{id}")
+    })
+  }
+}
+
+struct WindowEntry<EventT: 'static> {
+  window: Rc<Window>,
+  mode: UpdateMode,
+  app: Box<dyn App<EventT>>,
+}
+
+/// The single [`ApplicationHandler`] that actually drives the shared
+/// loop, dispatching each `WindowEvent` to the [`App`] whose window id
+/// matches and fanning `user_event`s out to every widget.
+struct RootHandler<EventT: 'static> {
+  pending: Vec<shared_loop::PendingWindow<EventT>>,
+  windows: Vec<WindowEntry<EventT>>,
+  ever_resumed: bool,
+}
+
+impl<EventT: Clone + 'static> RootHandler<EventT> {
+  /// Builds each pending window and hands its `App` the first `resumed`
+  /// call, shared by the initial drain in [`resumed`](Self::resumed)
+  /// and by widgets that register after the loop has already started.
+  /// Skips any entry that was meanwhile cancelled by an unmounting
+  /// widget, so a window never gets built just to be orphaned.
+  fn spawn_windows(
+    &mut self,
+    event_loop: &ActiveEventLoop,
+    pending: Vec<shared_loop::PendingWindow<EventT>>,
+  ) {
+    for pending in pending {
+      if pending.cancelled.get() {
+        continue;
+      }
+      let window = Rc::new(
+        event_loop
+          .create_window(pending.attributes)
+          .expect("Failed to initialize winit window"),
+      );
+      let mut app = pending.app;
+      app.resumed(window.clone());
+      self.windows.push(WindowEntry { window, mode: pending.mode, app });
+    }
+  }
+
+  /// Drops any window whose `Winit` widget unmounted since the last
+  /// time this ran.
+  fn drop_unregistered(&mut self) {
+    for id in shared_loop::take_unregistered() {
+      self.windows.retain(|entry| entry.window.id() != id);
+    }
+  }
+}
+
+impl<EventT: Clone + 'static> ApplicationHandler<EventT> for RootHandler<EventT> {
+  fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+    // Re-resuming after a suspend: give existing windows a chance to
+    // recreate whatever surface they dropped in `suspended`.
+    if self.ever_resumed {
+      for entry in &mut self.windows {
+        entry.app.resumed(entry.window.clone());
+      }
+    }
+    let pending = std::mem::take(&mut self.pending);
+    self.spawn_windows(event_loop, pending);
+    self.ever_resumed = true;
+  }
+
+  fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+    for entry in &mut self.windows {
+      entry.app.suspended();
+    }
+  }
+
+  fn window_event(
+    &mut self,
+    _event_loop: &ActiveEventLoop,
+    window_id: WindowId,
+    event: WindowEvent,
+  ) {
+    if let Some(entry) =
+      self.windows.iter_mut().find(|entry| entry.window.id() == window_id)
+    {
+      entry.app.window_event(&entry.window, event);
+    }
+  }
+
+  fn user_event(&mut self, event_loop: &ActiveEventLoop, event: EventT) {
+    let late = shared_loop::take_late_pending::<EventT>();
+    self.spawn_windows(event_loop, late);
+    self.drop_unregistered();
+    for entry in &mut self.windows {
+      entry.app.user_event(event.clone());
+    }
+  }
+
+  fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+    let late = shared_loop::take_late_pending::<EventT>();
+    self.spawn_windows(event_loop, late);
+    self.drop_unregistered();
+    let continuous = self.windows.iter().any(|entry| entry.mode == UpdateMode::Continuous);
+    event_loop.set_control_flow(if continuous {
+      ControlFlow::Poll
+    } else {
+      ControlFlow::Wait
+    });
+    for entry in &mut self.windows {
+      entry.app.about_to_wait(&entry.window);
+      if entry.mode == UpdateMode::Continuous {
+        entry.window.request_redraw();
+      }
+    }
+  }
+}
+
+/// Wraps a widget's [`App`] to also stash its `Window` somewhere a
+/// Leptos effect can reach, so `width`/`height`/`alt` stay reactive
+/// even though the real `Window` isn't created until `resumed` fires.
+struct ReactiveApp<AppT, EventT: 'static> {
+  inner: AppT,
+  window: Rc<RefCell<Option<Rc<Window>>>>,
+  _phantom: PhantomData<EventT>,
+}
+
+impl<AppT, EventT> App<EventT> for ReactiveApp<AppT, EventT>
+where
+  AppT: App<EventT>,
+{
+  fn resumed(&mut self, window: Rc<Window>) {
+    *self.window.borrow_mut() = Some(window.clone());
+    self.inner.resumed(window);
+  }
+
+  fn suspended(&mut self) {
+    self.inner.suspended();
+  }
+
+  fn window_event(&mut self, window: &Rc<Window>, event: WindowEvent) {
+    self.inner.window_event(window, event);
+  }
+
+  fn user_event(&mut self, event: EventT) {
+    self.inner.user_event(event);
+  }
+
+  fn about_to_wait(&mut self, window: &Rc<Window>) {
+    self.inner.about_to_wait(window);
+  }
+}
 
 /// # Props explained:
-/// * `program`:
-/// An async function pointer to the window's event loop
-/// implementation.
+/// * `app`:
+/// The widget's windowing logic, implementing [`App<EventT>`].
 ///
 /// * `width` and `height`:
 /// Reactively change the window's dimensions. Both default
-/// to 500.
+/// to 500. Ignored while `fill_parent` is set.
+///
+/// * `fill_parent`:
+/// When `true`, ignore `width`/`height` and instead track the
+/// canvas's parent element via a [`ResizeObserver`](web_sys::ResizeObserver),
+/// resizing the `Window` to the parent's content box (scaled by
+/// `devicePixelRatio`) on every observed resize. Lets the window fill
+/// a flexbox/grid cell instead of using fixed dimensions.
 ///
 /// * `alt`:
 /// Reactively change the window's title, which in practice
 /// changes the `alt` property of the canvas element.
 ///
+/// * `mode`:
+/// [`UpdateMode::Continuous`] (default) redraws every loop
+/// iteration; [`UpdateMode::Reactive`] only redraws in response to
+/// input, which saves battery/CPU on the web.
+///
+/// * `window_mode`:
+/// Reactively switch between [`WindowMode::Windowed`] (default),
+/// [`WindowMode::BorderlessFullscreen`] and [`WindowMode::Fullscreen`],
+/// e.g. for a game's fullscreen toggle. Calls `window.set_fullscreen`,
+/// which on the web goes through the Fullscreen Browser API.
+///
+/// * `resizable`:
+/// Reactively toggles whether the window can be resized by the user.
+/// Defaults to `true`.
+///
+/// * `decorations`:
+/// Reactively toggles the window's title bar/border. Defaults to
+/// `true`.
+///
+/// * `canvas`:
+/// A CSS selector (or element id) for an existing `<canvas>` already
+/// in your markup. When set, that element is bound to the `Window`
+/// instead of auto-creating one, and the widget itself renders
+/// nothing. Leave unset to get the default auto-created canvas.
+///
+/// * `events`:
+/// Optional signal used to push user events into the running
+/// loop. Whenever it changes to `Some(event)`, the event is
+/// sent through an [`EventLoopProxy`] and delivered to this
+/// (and every other) widget's [`App::user_event`].
+///
+/// * `on_frame`:
+/// Optional `Fn(f64, Rc<Window>)` registered as a recursive
+/// `requestAnimationFrame` callback, independent of the winit loop.
+/// Receives the frame timestamp and the `Window` so it can call
+/// `request_redraw()`. Useful when you want a steady paint cadence
+/// alongside (not instead of) winit's own event-driven redraws.
+///
 /// * '_phantom':
 /// Ignore, contains the user event type for the EventLoop
 #[cfg(target_arch = "wasm32")]
 #[component]
-pub fn Winit<FunctionT, FutureT, EventT>(
-  program: FunctionT,
+pub fn Winit<AppT, EventT>(
+  app: AppT,
   #[prop(into, default = 500.into())] width: MaybeSignal<u32>,
   #[prop(into, default = 500.into())] height: MaybeSignal<u32>,
+  #[prop(optional)] fill_parent: bool,
   #[prop(into, default = "Winit Window".into())] alt: MaybeSignal<String>,
+  #[prop(optional)] mode: UpdateMode,
+  #[prop(into, default = WindowMode::Windowed.into())] window_mode: MaybeSignal<WindowMode>,
+  #[prop(into, default = true.into())] resizable: MaybeSignal<bool>,
+  #[prop(into, default = true.into())] decorations: MaybeSignal<bool>,
+  #[prop(into, optional)] canvas: MaybeSignal<Option<String>>,
+  #[prop(optional)] events: Option<Signal<Option<EventT>>>,
+  #[prop(into, optional)] on_frame: Option<OnFrame>,
   #[prop(optional)] _phantom: PhantomData<&'static EventT>,
 ) -> impl IntoView
 where
-  EventT: 'static,
-  FutureT: Future<Output = ()>,
-  FunctionT: Fn(EventLoop<EventT>, Rc<Window>) -> FutureT + 'static,
+  EventT: Clone + 'static,
+  AppT: App<EventT> + 'static,
 {
-  match leptos_dom::document().get_element_by_id("Winit") {
-    Some(_) => {
-      log::error!(
-        "leptos_winit: You might be trying to create multiple Winit widgets. \
-         See the docs to understand why this is not allowed"
-      );
-      return None;
+  use wasm_bindgen::JsCast;
+
+  // Bind to an existing canvas when a selector is given, otherwise
+  // auto-create one. Each auto-created canvas gets its own id so
+  // several `Winit` components can coexist on one page instead of
+  // colliding on a hard-coded id. Resolving the selector (and building
+  // the window around whichever canvas we end up with) is deferred to
+  // a microtask: a component's returned view has no parent until its
+  // caller attaches it after this function returns, so a selector
+  // naming a sibling `<canvas>` in the very same `view!` tree isn't
+  // reachable yet at this point.
+  let (canvas_source, view) = match canvas.get_untracked() {
+    Some(selector) => (CanvasSource::Selector(selector), None),
+    None => {
+      let node_ref = create_node_ref::<Canvas>();
+      let canvas = leptos::html::canvas().id(shared_loop::next_canvas_id());
+      canvas.node_ref(node_ref);
+      (CanvasSource::NodeRef(node_ref), node_ref.get_untracked())
     },
-    None => {},
   };
 
-  use winit::platform::web::WindowBuilderExtWebSys;
-  // Initializing canvas element
-  let canvas_ref = {
-    let node_ref = create_node_ref::<Canvas>();
-    let canvas = leptos::html::canvas().id("Winit");
-    canvas.node_ref(node_ref);
-    node_ref
-  };
-  use wasm_bindgen::JsCast;
-  let canvas_element = web_sys::Element::from(
-    canvas_ref
-      .get_untracked()
-      .expect("Failed to reference canvas element")
-      .unchecked_ref::<HtmlCanvasElement>()
-      .clone(),
-  );
-
-  // Basic winit setup
-  log::warn!("Creating EventLoop, make sure this happens only once!");
-  let event_loop: EventLoop<EventT> =
-    EventLoopBuilder::with_user_event().build();
-  let window = Rc::new(
-    winit::window::WindowBuilder::new()
-      .with_canvas(Some(canvas_element.unchecked_into()))
-      .with_title(&alt.get_untracked())
-      .with_inner_size(winit::dpi::LogicalSize::new(
-        width.get_untracked(),
-        height.get_untracked(),
-      ))
-      .build(&event_loop)
-      .expect("Failed to initialize winit window"),
-  );
-
-  // Reactively update size and title
+  let window_slot: Rc<RefCell<Option<Rc<Window>>>> = Rc::new(RefCell::new(None));
+  let proxy_slot: Rc<RefCell<Option<EventLoopProxy<EventT>>>> =
+    Rc::new(RefCell::new(None));
+  // Set by `on_cleanup` below. Checked by the deferred microtask so a
+  // widget that unmounts before that microtask even runs doesn't still
+  // go on to register (and then orphan) a window nothing asked for
+  // anymore, and handed to `register_window` as the pending window's
+  // `cancelled` flag so the same unmount also cancels a registration
+  // that's already queued but not yet built (see `shared_loop::PendingWindow`).
+  let disposed: Rc<Cell<bool>> = Rc::new(Cell::new(false));
+
+  // Capture this component's reactive owner so `on_cleanup` below still
+  // attaches to it even though it's called from inside `queue_microtask`,
+  // after this function (and the owner context that's current for its
+  // duration) has already returned.
+  let owner = Owner::current().expect("Winit must be called within a reactive owner");
+
+  {
+    let window_slot = window_slot.clone();
+    let proxy_slot = proxy_slot.clone();
+    let disposed = disposed.clone();
+    let owner = owner.clone();
+    queue_microtask(move || {
+      if disposed.get() {
+        return;
+      }
+      use winit::platform::web::WindowAttributesExtWebSys;
+
+      let canvas_element: HtmlCanvasElement = match canvas_source {
+        CanvasSource::Selector(selector) => {
+          let element = leptos_dom::document()
+            .query_selector(&selector)
+            .ok()
+            .flatten()
+            .or_else(|| leptos_dom::document().get_element_by_id(&selector))
+            .unwrap_or_else(|| {
+              panic!(
+                "leptos_winit: no element found for canvas selector `{selector}`"
+              )
+            });
+          element.dyn_into::<HtmlCanvasElement>().unwrap_or_else(|element| {
+            panic!(
+              "leptos_winit: the element matched by canvas selector \
+               `{selector}` is a <{}>, not a <canvas>",
+              element.tag_name().to_lowercase()
+            )
+          })
+        },
+        CanvasSource::NodeRef(node_ref) => node_ref
+          .get_untracked()
+          .expect("Failed to reference canvas element")
+          .unchecked_ref::<HtmlCanvasElement>()
+          .clone(),
+      };
+
+      let canvas_element_for_resize = web_sys::Element::from(canvas_element.clone());
+      let attributes = Window::default_attributes()
+        .with_canvas(Some(canvas_element))
+        .with_title(&alt.get_untracked())
+        .with_inner_size(winit::dpi::LogicalSize::new(
+          width.get_untracked(),
+          height.get_untracked(),
+        ))
+        .with_fullscreen(window_mode_to_fullscreen(window_mode.get_untracked()))
+        .with_resizable(resizable.get_untracked())
+        .with_decorations(decorations.get_untracked());
+      let reactive_app = ReactiveApp {
+        inner: app,
+        window: window_slot.clone(),
+        _phantom: PhantomData,
+      };
+      let proxy = shared_loop::register_window(
+        attributes,
+        mode,
+        Box::new(reactive_app),
+        disposed.clone(),
+      );
+      *proxy_slot.borrow_mut() = Some(proxy);
+
+      // Track the canvas's parent element and resize the Window to
+      // match it on every observed resize, instead of using fixed
+      // dimensions. Deferred one more tick past the canvas itself
+      // being resolved, since an auto-created canvas's parent is only
+      // guaranteed to exist once the rest of the view finished mounting.
+      if fill_parent {
+        let window_slot = window_slot.clone();
+        let closure = wasm_bindgen::closure::Closure::<dyn FnMut(js_sys::Array)>::new(
+          move |entries: js_sys::Array| {
+            let Some(entry) = entries.get(0).dyn_ref::<web_sys::ResizeObserverEntry>().cloned() else {
+              return;
+            };
+            let device_pixel_ratio = leptos_dom::window().device_pixel_ratio();
+            let content_rect = entry.content_rect();
+            let physical_size = winit::dpi::PhysicalSize::new(
+              (content_rect.width() * device_pixel_ratio).round() as u32,
+              (content_rect.height() * device_pixel_ratio).round() as u32,
+            );
+            if let Some(window) = window_slot.borrow().as_ref() {
+              window.set_inner_size(physical_size);
+              window.request_redraw();
+            }
+          },
+        );
+        let observer =
+          web_sys::ResizeObserver::new(closure.as_ref().unchecked_ref())
+            .expect("Failed to create ResizeObserver");
+        {
+          let observer = observer.clone();
+          queue_microtask(move || {
+            if let Some(parent) = canvas_element_for_resize.parent_element() {
+              observer.observe(&parent);
+            } else {
+              log::error!(
+                "leptos_winit: fill_parent is set but the canvas has no parent \
+                 element to observe"
+              );
+            }
+          });
+        }
+        owner.with(|| {
+          on_cleanup(move || {
+            observer.disconnect();
+            drop(closure);
+          });
+        });
+      }
+
+      // Only the first `Winit` widget to mount drives the shared loop;
+      // it owns every other widget's `Window` too, which dispatches
+      // events by `WindowId`. The first widget claims it right after its
+      // own registration above, before other widgets' microtasks have
+      // registered theirs, so the take is
+      // deferred one more microtask tick past this one, giving every
+      // `Winit` widget mounted in this same synchronous render pass a
+      // chance to register its window before the loop starts running.
+      if shared_loop::claim_driver() {
+        queue_microtask(move || {
+          let (event_loop, pending) = shared_loop::take_state::<EventT>();
+          let mut handler = RootHandler {
+            pending,
+            windows: Vec::new(),
+            ever_resumed: false,
+          };
+          if let Err(error) = event_loop.run_app(&mut handler) {
+            log::error!("leptos_winit: EventLoop exited with an error: {error}");
+          }
+        });
+      }
+    });
+  }
+
+  // Tell the shared loop to drop this widget's window (and stop
+  // dispatching events/redraws to it) once the widget unmounts, so a
+  // `<Show>` toggled off or a route navigated away from doesn't leak
+  // its window forever. Setting `disposed` also cancels the window if
+  // it's still queued and hasn't been created yet (see `register_window`
+  // and `shared_loop::PendingWindow::cancelled`).
+  {
+    let window_slot = window_slot.clone();
+    let disposed = disposed.clone();
+    on_cleanup(move || {
+      disposed.set(true);
+      if let Some(window) = window_slot.borrow().as_ref() {
+        shared_loop::unregister_window(window.id());
+      }
+    });
+  }
+
+  // Reactively update the title once the window exists. It's created
+  // lazily in `resumed`, so a change made before that is simply
+  // covered by `attributes` above.
   {
-    let window = window.clone();
+    let window_slot = window_slot.clone();
     create_effect(move |_| {
-      window.set_inner_size(winit::dpi::LogicalSize::new(
-        width.get(),
-        height.get(),
-      ));
-      window.set_title(&alt.get());
+      let title = alt.get();
+      if let Some(window) = window_slot.borrow().as_ref() {
+        window.set_title(&title);
+      }
     });
   }
 
-  // Run event loop async
-  spawn_local(async move {
-    program(event_loop, window).await;
-    log::warn!("Winit EventLoop exited");
-  });
+  // Reactively update the size the same way, unless `fill_parent` is
+  // driving it off the canvas's parent element instead.
+  if !fill_parent {
+    let window_slot = window_slot.clone();
+    create_effect(move |_| {
+      let size = winit::dpi::LogicalSize::new(width.get(), height.get());
+      if let Some(window) = window_slot.borrow().as_ref() {
+        window.set_inner_size(size);
+      }
+    });
+  }
+
+  // Reactively update window mode, resizability and decorations. The
+  // initial values are already set via `attributes` above.
+  {
+    let window_slot = window_slot.clone();
+    create_effect(move |_| {
+      let fullscreen = window_mode_to_fullscreen(window_mode.get());
+      let resizable = resizable.get();
+      let decorations = decorations.get();
+      if let Some(window) = window_slot.borrow().as_ref() {
+        window.set_fullscreen(fullscreen);
+        window.set_resizable(resizable);
+        window.set_decorations(decorations);
+      }
+    });
+  }
+
+  // Drive `on_frame` off its own recursive requestAnimationFrame loop,
+  // entirely independent of `event_loop.run_app`.
+  if let Some(OnFrame(on_frame)) = on_frame {
+    let window_slot = window_slot.clone();
+    let raf_window = leptos_dom::window();
+    let raf_handle: Rc<RefCell<Option<i32>>> = Rc::new(RefCell::new(None));
+    let raf_closure: Rc<RefCell<Option<wasm_bindgen::closure::Closure<dyn FnMut(f64)>>>> =
+      Rc::new(RefCell::new(None));
+    *raf_closure.borrow_mut() = Some({
+      let raf_window = raf_window.clone();
+      let raf_handle = raf_handle.clone();
+      let raf_closure = raf_closure.clone();
+      wasm_bindgen::closure::Closure::<dyn FnMut(f64)>::new(move |timestamp: f64| {
+        if let Some(window) = window_slot.borrow().as_ref() {
+          on_frame(timestamp, window.clone());
+        }
+        let handle = raf_window
+          .request_animation_frame(
+            raf_closure.borrow().as_ref().unwrap().as_ref().unchecked_ref(),
+          )
+          .expect("Failed to schedule requestAnimationFrame");
+        *raf_handle.borrow_mut() = Some(handle);
+      })
+    });
+    let handle = raf_window
+      .request_animation_frame(
+        raf_closure.borrow().as_ref().unwrap().as_ref().unchecked_ref(),
+      )
+      .expect("Failed to schedule requestAnimationFrame");
+    *raf_handle.borrow_mut() = Some(handle);
+
+    on_cleanup(move || {
+      if let Some(handle) = raf_handle.borrow_mut().take() {
+        let _ = raf_window.cancel_animation_frame(handle);
+      }
+      raf_closure.borrow_mut().take();
+    });
+  }
+
+  // Forward events from the Leptos tree into the running loop. The
+  // very first run of this effect can race the microtask that fills
+  // `proxy_slot`, so an event already present at mount is logged
+  // rather than silently dropped.
+  if let Some(events) = events {
+    let proxy_slot = proxy_slot.clone();
+    create_effect(move |_| {
+      if let Some(event) = events.get() {
+        match proxy_slot.borrow().as_ref() {
+          Some(proxy) => {
+            if proxy.send_event(event).is_err() {
+              log::error!(
+                "leptos_winit: failed to send event, EventLoop already exited"
+              );
+            }
+          },
+          None => {
+            log::error!(
+              "leptos_winit: dropped an event, the window isn't registered \
+               with the shared EventLoop yet"
+            );
+          },
+        }
+      }
+    });
+  }
 
-  canvas_ref.get_untracked()
+  view
 }